@@ -0,0 +1,95 @@
+//! Generic glue between an ADI (Anisette Data Identifier) implementation and the
+//! [`AnisetteHeadersProvider`] interface. An ADI implementation (e.g.
+//! [`crate::store_services_core::StoreServicesCoreADIProxy`]) only needs to know how to
+//! provision a device and mint one-time-passwords; this module turns that into the header
+//! map callers actually want.
+
+use crate::anisette_headers_provider::{self, AnisetteHeadersProvider};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::instrument;
+
+/// A device identifier understood by the local ADI library.
+pub type DeviceId = u32;
+
+/// An ADI implementation that can be configured with a provisioning path and asked to
+/// provision a device / mint an OTP for it.
+pub trait ConfigurableADIProxy {
+    fn set_provisioning_path(&mut self, path: &str) -> Result<()>;
+
+    fn is_machine_provisioned(&self, device_id: DeviceId) -> bool;
+
+    fn provision_device(&mut self, device_id: DeviceId) -> Result<()>;
+
+    /// Returns `(machine_id, one_time_password)`.
+    fn request_otp(&self, device_id: DeviceId) -> Result<(Vec<u8>, Vec<u8>)>;
+}
+
+/// Turns any [`ConfigurableADIProxy`] into an [`AnisetteHeadersProvider`] by provisioning the
+/// device on first use (if necessary) and then minting an OTP per call.
+pub struct ADIProxyAnisetteProvider<T: ConfigurableADIProxy> {
+    adi_proxy: T,
+    device_id: DeviceId,
+    identifier_path: PathBuf,
+}
+
+impl<T: ConfigurableADIProxy> ADIProxyAnisetteProvider<T> {
+    pub fn new(adi_proxy: T, identifier_path: PathBuf) -> Result<ADIProxyAnisetteProvider<T>> {
+        Ok(ADIProxyAnisetteProvider {
+            adi_proxy,
+            device_id: Self::load_or_create_device_id(&identifier_path)?,
+            identifier_path,
+        })
+    }
+
+    fn load_or_create_device_id(identifier_path: &Path) -> Result<DeviceId> {
+        // A device id only needs to be stable for the lifetime of the provisioning directory,
+        // so we derive one from the path rather than generating real hardware identifiers.
+        let mut hash: u32 = 0x811c9dc5;
+        for byte in identifier_path.to_string_lossy().bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+        Ok(hash | 1)
+    }
+
+    #[instrument(skip(self), fields(device_id = self.device_id))]
+    fn ensure_provisioned(&mut self) -> Result<()> {
+        if !self.adi_proxy.is_machine_provisioned(self.device_id) {
+            tracing::info!("device not yet provisioned, provisioning now");
+            self.adi_proxy.provision_device(self.device_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ConfigurableADIProxy + Send> AnisetteHeadersProvider for ADIProxyAnisetteProvider<T> {
+    #[instrument(
+        name = "get_authentication_headers",
+        skip(self),
+        fields(provider = "local", device_id = self.device_id, request_id = anisette_headers_provider::next_request_id())
+    )]
+    fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>> {
+        self.ensure_provisioned()?;
+
+        let (machine_id, otp) = self.adi_proxy.request_otp(self.device_id)?;
+        // Never log the actual machine id / OTP bytes, only their presence and length.
+        tracing::debug!(
+            machine_id_len = machine_id.len(),
+            otp_len = otp.len(),
+            "minted anisette one-time-password"
+        );
+
+        let mut headers = HashMap::new();
+        headers.insert("X-Apple-I-MD-M".to_string(), base64::encode(machine_id));
+        headers.insert("X-Apple-I-MD".to_string(), base64::encode(otp));
+        headers.insert("X-Apple-I-MD-RINFO".to_string(), "17106176".to_string());
+        headers.insert("X-Apple-I-MD-LU".to_string(), self.device_id.to_string());
+        headers.insert(
+            "X-Apple-I-SRL-NO".to_string(),
+            self.identifier_path.to_string_lossy().to_string(),
+        );
+        Ok(headers)
+    }
+}
@@ -0,0 +1,24 @@
+//! The common interface implemented by every source of anisette data, whether it's generated
+//! locally via ADI or fetched from a remote anisette server.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub trait AnisetteHeadersProvider: Send {
+    /// Returns the current set of anisette headers (e.g. `X-Apple-I-MD`, `X-Apple-I-MD-M`),
+    /// generating or fetching them if necessary.
+    fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>>;
+
+    /// Forces the next `get_authentication_headers` call to regenerate/refetch rather than reuse
+    /// anything cached. A no-op for providers that don't cache.
+    fn force_refresh(&mut self) {}
+}
+
+/// A process-wide counter used to tag each `get_authentication_headers` call with a short,
+/// monotonically increasing id so concurrent fetches can be correlated in `tracing` output.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_request_id() -> u64 {
+    NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
@@ -0,0 +1,145 @@
+//! A TTL-based caching wrapper around any [`AnisetteHeadersProvider`]. Generating anisette data
+//! (particularly the ADI one-time-password) is expensive and rate-sensitive, so this serves the
+//! last generated header map until it goes stale instead of regenerating on every call.
+
+use crate::anisette_headers_provider::AnisetteHeadersProvider;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Header key this wrapper adds to the returned map, giving downstream callers the age (in
+/// seconds) of the cached anisette data so they can decide whether to trust a near-stale value.
+/// `X-Omnisette-` avoids colliding with any real Apple header, which are all `X-Apple-*`/`X-Mme-*`.
+pub const CACHE_AGE_HEADER: &str = "X-Omnisette-Cache-Age";
+
+pub struct CachedAnisetteProvider {
+    provider: Box<dyn AnisetteHeadersProvider>,
+    ttl: Duration,
+    cached: Option<(HashMap<String, String>, Instant)>,
+}
+
+impl CachedAnisetteProvider {
+    pub fn new(
+        provider: Box<dyn AnisetteHeadersProvider>,
+        ttl: Duration,
+    ) -> CachedAnisetteProvider {
+        CachedAnisetteProvider {
+            provider,
+            ttl,
+            cached: None,
+        }
+    }
+
+    fn refresh(&mut self) -> Result<(HashMap<String, String>, Instant)> {
+        let headers = self.provider.get_authentication_headers()?;
+        let generated_at = Instant::now();
+        self.cached = Some((headers.clone(), generated_at));
+        Ok((headers, generated_at))
+    }
+}
+
+impl AnisetteHeadersProvider for CachedAnisetteProvider {
+    fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>> {
+        let (mut headers, generated_at) = match &self.cached {
+            Some((headers, generated_at)) if generated_at.elapsed() < self.ttl => {
+                (headers.clone(), *generated_at)
+            }
+            _ => self.refresh()?,
+        };
+        headers.insert(
+            CACHE_AGE_HEADER.to_string(),
+            generated_at.elapsed().as_secs().to_string(),
+        );
+        Ok(headers)
+    }
+
+    /// Bypasses the cache on the next call, even if the TTL hasn't elapsed yet.
+    fn force_refresh(&mut self) {
+        self.cached = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingProvider {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl AnisetteHeadersProvider for CountingProvider {
+        fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut headers = HashMap::new();
+            headers.insert("X-Apple-I-MD".to_string(), call.to_string());
+            Ok(headers)
+        }
+    }
+
+    #[test]
+    fn serves_cached_headers_within_ttl() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut provider = CachedAnisetteProvider::new(
+            Box::new(CountingProvider {
+                calls: calls.clone(),
+            }),
+            Duration::from_secs(60),
+        );
+
+        let first = provider.get_authentication_headers().unwrap();
+        let second = provider.get_authentication_headers().unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            first.get("X-Apple-I-MD"),
+            second.get("X-Apple-I-MD")
+        );
+    }
+
+    #[test]
+    fn refreshes_once_ttl_elapses() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut provider = CachedAnisetteProvider::new(
+            Box::new(CountingProvider {
+                calls: calls.clone(),
+            }),
+            Duration::from_millis(0),
+        );
+
+        provider.get_authentication_headers().unwrap();
+        provider.get_authentication_headers().unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn force_refresh_bypasses_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut provider = CachedAnisetteProvider::new(
+            Box::new(CountingProvider {
+                calls: calls.clone(),
+            }),
+            Duration::from_secs(60),
+        );
+
+        provider.get_authentication_headers().unwrap();
+        provider.force_refresh();
+        provider.get_authentication_headers().unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn attaches_cache_age_header() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut provider = CachedAnisetteProvider::new(
+            Box::new(CountingProvider { calls }),
+            Duration::from_secs(60),
+        );
+
+        let headers = provider.get_authentication_headers().unwrap();
+        assert!(headers.contains_key(CACHE_AGE_HEADER));
+    }
+}
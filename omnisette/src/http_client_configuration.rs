@@ -0,0 +1,158 @@
+//! Knobs for the `reqwest` client(s) the remote anisette providers build on demand. Kept as its
+//! own configuration object (rather than a single shared client) so that a provider created in
+//! one async runtime and handed to another doesn't end up reusing a client bound to the wrong
+//! runtime.
+
+use anyhow::Result;
+use std::time::Duration;
+
+#[derive(Clone, Default)]
+pub struct HttpClientConfiguration {
+    request_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    root_certificates_pem: Vec<Vec<u8>>,
+    user_agent: Option<String>,
+    client: Option<reqwest::Client>,
+}
+
+impl HttpClientConfiguration {
+    pub fn set_request_timeout(mut self, timeout: Duration) -> HttpClientConfiguration {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn set_connect_timeout(mut self, timeout: Duration) -> HttpClientConfiguration {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn set_proxy(mut self, proxy: String) -> HttpClientConfiguration {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn add_root_certificate_pem(mut self, pem: Vec<u8>) -> HttpClientConfiguration {
+        self.root_certificates_pem.push(pem);
+        self
+    }
+
+    pub fn set_user_agent(mut self, user_agent: String) -> HttpClientConfiguration {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Escape hatch for callers who already manage a `reqwest::Client` (e.g. to share connection
+    /// pooling with the rest of their application). When set, [`HttpClientConfiguration::build_client`]
+    /// returns this client as-is and ignores every other knob on this struct;
+    /// [`HttpClientConfiguration::build_blocking_client`] ignores this client and still applies
+    /// the other knobs.
+    pub fn set_http_client(mut self, client: reqwest::Client) -> HttpClientConfiguration {
+        self.client = Some(client);
+        self
+    }
+
+    /// Builds an async client honoring the configured knobs, unless a client was supplied via
+    /// [`HttpClientConfiguration::set_http_client`]. Called fresh by each provider rather than
+    /// memoized, so a built client is bound to the runtime it's used from. A client supplied via
+    /// `set_http_client` is returned as a clone of that same client on every call.
+    pub fn build_client(&self) -> Result<reqwest::Client> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+        let mut builder = reqwest::Client::builder();
+        builder = self.apply_common(builder)?;
+        Ok(builder.build()?)
+    }
+
+    /// Builds a blocking client honoring the configured knobs. The [`set_http_client`] escape
+    /// hatch only applies to the async client, since a blocking client can't be built from one.
+    pub fn build_blocking_client(&self) -> Result<reqwest::blocking::Client> {
+        let mut builder = reqwest::blocking::Client::builder();
+        builder = self.apply_common_blocking(builder)?;
+        Ok(builder.build()?)
+    }
+
+    fn apply_common(&self, mut builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder> {
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        for pem in &self.root_certificates_pem {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(pem)?);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent.clone());
+        }
+        Ok(builder)
+    }
+
+    fn apply_common_blocking(
+        &self,
+        mut builder: reqwest::blocking::ClientBuilder,
+    ) -> Result<reqwest::blocking::ClientBuilder> {
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        for pem in &self.root_certificates_pem {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(pem)?);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent.clone());
+        }
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_client_honors_configured_knobs() {
+        let config = HttpClientConfiguration::default()
+            .set_request_timeout(Duration::from_secs(5))
+            .set_connect_timeout(Duration::from_secs(2))
+            .set_user_agent("omnisette-test".to_string());
+
+        assert!(config.build_client().is_ok());
+        assert!(config.build_blocking_client().is_ok());
+    }
+
+    #[test]
+    fn invalid_proxy_is_a_build_error() {
+        let config = HttpClientConfiguration::default().set_proxy("not a url".to_string());
+
+        assert!(config.build_client().is_err());
+        assert!(config.build_blocking_client().is_err());
+    }
+
+    #[test]
+    fn invalid_root_certificate_is_a_build_error() {
+        let config =
+            HttpClientConfiguration::default().add_root_certificate_pem(b"not a pem".to_vec());
+
+        assert!(config.build_client().is_err());
+        assert!(config.build_blocking_client().is_err());
+    }
+
+    #[test]
+    fn set_http_client_bypasses_other_knobs() {
+        let config = HttpClientConfiguration::default()
+            .set_proxy("not a url".to_string())
+            .set_http_client(reqwest::Client::new());
+
+        assert!(config.build_client().is_ok());
+    }
+}
@@ -3,17 +3,32 @@
 //! If you want an async API, enable the `async` feature.
 //!
 //! If you want remote anisette, make sure the `remote-anisette` feature is enabled. (it's currently on by default)
+//!
+//! Provisioning and fetch operations are instrumented with `tracing` spans (anisette output
+//! itself is never logged, only its presence/length). If your application still relies on the
+//! `log` facade (e.g. a `simplelog`/`env_logger` sink with no `tracing::Subscriber`), depend on
+//! `tracing` with its `log` feature enabled so those `tracing::debug!`/`info!` calls are also
+//! emitted through `log` — there's no runtime call needed for that, it's a Cargo feature of the
+//! `tracing` crate itself.
 
 use crate::adi_proxy::{ADIProxyAnisetteProvider, ConfigurableADIProxy};
 use crate::anisette_headers_provider::AnisetteHeadersProvider;
-use anyhow::Result;
+use crate::cached_anisette_provider::CachedAnisetteProvider;
+#[cfg(feature = "remote-anisette")]
+use anyhow::Context;
+use anyhow::{bail, Result};
 use std::fmt::Formatter;
 use std::path::PathBuf;
+use std::time::Duration;
 
 pub mod adi_proxy;
 pub mod anisette_headers_provider;
+pub mod cached_anisette_provider;
 pub mod store_services_core;
 
+#[cfg(any(feature = "remote-anisette", feature = "remote-anisette-v3"))]
+pub mod http_client_configuration;
+
 #[cfg(feature = "remote-anisette-v3")]
 pub mod remote_anisette_v3;
 
@@ -23,6 +38,9 @@ pub mod aos_kit;
 #[cfg(feature = "remote-anisette")]
 pub mod remote_anisette;
 
+#[cfg(feature = "server")]
+pub mod server;
+
 #[allow(dead_code)]
 pub struct AnisetteHeaders;
 
@@ -47,10 +65,39 @@ pub const DEFAULT_ANISETTE_URL: &str = "https://ani.wesbryie.com/";
 #[cfg(feature = "remote-anisette-v3")]
 pub const DEFAULT_ANISETTE_URL_V3: &str = "https://ani.sidestore.io";
 
+/// Default TTL for cached headers, matching the ~30s cadence of the ADI one-time-password.
+pub const DEFAULT_HEADER_TTL: Duration = Duration::from_secs(30);
+
+/// Controls whether [`AnisetteHeaders::get_anisette_headers_provider`] is allowed to fall back
+/// between local (`StoreServicesCore`) and remote anisette provisioning, mirroring the explicit
+/// Deny/Allow failure-mode configuration pattern used elsewhere for service configuration.
+///
+/// The `Prefer*` variants keep today's behavior of trying one source and silently falling back
+/// to the other; the `*Only` variants make the failure mode explicit for callers (e.g.
+/// security-sensitive integrations) that must not leak device identity to a remote server, or
+/// conversely must never touch local provisioning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProviderPolicy {
+    /// Only attempt local provisioning. Propagate its error verbatim if it fails.
+    LocalOnly,
+    /// Prefer local provisioning, falling back to the remote anisette server if it fails. This
+    /// is the historical default behavior of this crate.
+    #[default]
+    PreferLocal,
+    /// Only attempt the remote anisette server. Propagate its error verbatim if it fails.
+    RemoteOnly,
+    /// Prefer the remote anisette server, falling back to local provisioning if it fails.
+    PreferRemote,
+}
+
 #[derive(Clone)]
 pub struct AnisetteConfiguration {
     anisette_url: String,
     configuration_path: PathBuf,
+    provider_policy: ProviderPolicy,
+    header_ttl: Duration,
+    #[cfg(any(feature = "remote-anisette", feature = "remote-anisette-v3"))]
+    http_client_configuration: http_client_configuration::HttpClientConfiguration,
 }
 
 impl Default for AnisetteConfiguration {
@@ -64,6 +111,11 @@ impl AnisetteConfiguration {
         AnisetteConfiguration {
             anisette_url: DEFAULT_ANISETTE_URL.to_string(),
             configuration_path: PathBuf::new(),
+            provider_policy: ProviderPolicy::default(),
+            header_ttl: DEFAULT_HEADER_TTL,
+            #[cfg(any(feature = "remote-anisette", feature = "remote-anisette-v3"))]
+            http_client_configuration: http_client_configuration::HttpClientConfiguration::default(
+            ),
         }
     }
 
@@ -75,6 +127,10 @@ impl AnisetteConfiguration {
         &self.configuration_path
     }
 
+    pub fn provider_policy(&self) -> ProviderPolicy {
+        self.provider_policy
+    }
+
     pub fn set_anisette_url(mut self, anisette_url: String) -> AnisetteConfiguration {
         self.anisette_url = anisette_url;
         self
@@ -84,6 +140,34 @@ impl AnisetteConfiguration {
         self.configuration_path = configuration_path;
         self
     }
+
+    pub fn set_provider_policy(mut self, provider_policy: ProviderPolicy) -> AnisetteConfiguration {
+        self.provider_policy = provider_policy;
+        self
+    }
+
+    pub fn header_ttl(&self) -> Duration {
+        self.header_ttl
+    }
+
+    pub fn set_header_ttl(mut self, header_ttl: Duration) -> AnisetteConfiguration {
+        self.header_ttl = header_ttl;
+        self
+    }
+
+    #[cfg(any(feature = "remote-anisette", feature = "remote-anisette-v3"))]
+    pub fn http_client_configuration(&self) -> &http_client_configuration::HttpClientConfiguration {
+        &self.http_client_configuration
+    }
+
+    #[cfg(any(feature = "remote-anisette", feature = "remote-anisette-v3"))]
+    pub fn set_http_client_configuration(
+        mut self,
+        http_client_configuration: http_client_configuration::HttpClientConfiguration,
+    ) -> AnisetteConfiguration {
+        self.http_client_configuration = http_client_configuration;
+        self
+    }
 }
 
 pub enum AnisetteHeadersProviderType {
@@ -110,36 +194,100 @@ impl AnisetteHeadersProviderRes {
             provider_type: AnisetteHeadersProviderType::Remote,
         }
     }
+
+    /// Forces the next `get_authentication_headers` call to regenerate/refetch rather than reuse
+    /// anything cached. Delegates to the underlying provider; a no-op if it doesn't cache.
+    pub fn force_refresh(&mut self) {
+        self.provider.force_refresh();
+    }
 }
 
 impl AnisetteHeaders {
     pub fn get_anisette_headers_provider(
         configuration: AnisetteConfiguration,
     ) -> Result<AnisetteHeadersProviderRes> {
-        #[cfg(target_os = "macos")]
-        if let Ok(prov) = aos_kit::AOSKitAnisetteProvider::new() {
-            return Ok(AnisetteHeadersProviderRes::local(Box::new(prov)));
+        match configuration.provider_policy {
+            ProviderPolicy::LocalOnly => {
+                AnisetteHeaders::get_local_anisette_headers_provider(configuration)
+            }
+            ProviderPolicy::RemoteOnly => {
+                #[cfg(feature = "remote-anisette")]
+                return AnisetteHeaders::get_remote_anisette_headers_provider(configuration);
+                #[cfg(not(feature = "remote-anisette"))]
+                bail!(AnisetteMetaError::UnsupportedDevice)
+            }
+            ProviderPolicy::PreferLocal => {
+                match AnisetteHeaders::get_local_anisette_headers_provider(configuration.clone()) {
+                    Ok(provider) => Ok(provider),
+                    #[cfg(feature = "remote-anisette")]
+                    Err(local_err) => {
+                        AnisetteHeaders::get_remote_anisette_headers_provider(configuration)
+                            .with_context(|| {
+                                format!("local anisette provisioning failed: {local_err}")
+                            })
+                    }
+                    #[cfg(not(feature = "remote-anisette"))]
+                    Err(local_err) => Err(local_err),
+                }
+            }
+            #[cfg(feature = "remote-anisette")]
+            ProviderPolicy::PreferRemote => {
+                match AnisetteHeaders::get_remote_anisette_headers_provider(configuration.clone())
+                {
+                    Ok(provider) => Ok(provider),
+                    Err(remote_err) => {
+                        AnisetteHeaders::get_local_anisette_headers_provider(configuration)
+                            .with_context(|| {
+                                format!("remote anisette provisioning failed: {remote_err}")
+                            })
+                    }
+                }
+            }
+            #[cfg(not(feature = "remote-anisette"))]
+            ProviderPolicy::PreferRemote => {
+                AnisetteHeaders::get_local_anisette_headers_provider(configuration)
+            }
         }
+    }
 
-        // TODO: handle Err because it will just go to remote anisette and not tell the user anything
-        if let Ok(ssc_anisette_headers_provider) =
-            AnisetteHeaders::get_ssc_anisette_headers_provider(configuration.clone())
-        {
-            return Ok(ssc_anisette_headers_provider);
+    /// Tries every local (on-device) anisette source, in order of preference. Only reached for
+    /// [`ProviderPolicy`] variants that allow local provisioning at all; `RemoteOnly` never calls
+    /// this, so it can't pick up an on-device identity even when one is available.
+    pub fn get_local_anisette_headers_provider(
+        configuration: AnisetteConfiguration,
+    ) -> Result<AnisetteHeadersProviderRes> {
+        #[cfg(target_os = "macos")]
+        if let Ok(prov) = aos_kit::AOSKitAnisetteProvider::new() {
+            return Ok(AnisetteHeadersProviderRes::local(Box::new(
+                CachedAnisetteProvider::new(Box::new(prov), configuration.header_ttl()),
+            )));
         }
 
-        #[cfg(feature = "remote-anisette")]
-        return Ok(AnisetteHeadersProviderRes::remote(Box::new(
-            remote_anisette::RemoteAnisetteProvider::new(configuration.anisette_url),
-        )));
+        AnisetteHeaders::get_ssc_anisette_headers_provider(configuration)
+    }
 
-        #[cfg(not(feature = "remote-anisette"))]
-        bail!(AnisetteMetaError::UnsupportedDevice)
+    #[cfg(feature = "remote-anisette")]
+    pub fn get_remote_anisette_headers_provider(
+        configuration: AnisetteConfiguration,
+    ) -> Result<AnisetteHeadersProviderRes> {
+        let header_ttl = configuration.header_ttl();
+        Ok(AnisetteHeadersProviderRes::remote(Box::new(
+            CachedAnisetteProvider::new(
+                Box::new(
+                    remote_anisette::RemoteAnisetteProvider::with_http_client_configuration(
+                        configuration.anisette_url,
+                        configuration.http_client_configuration,
+                    ),
+                ),
+                header_ttl,
+            ),
+        )))
     }
 
     pub fn get_ssc_anisette_headers_provider(
         configuration: AnisetteConfiguration,
     ) -> Result<AnisetteHeadersProviderRes> {
+        let header_ttl = configuration.header_ttl();
         let mut ssc_adi_proxy = store_services_core::StoreServicesCoreADIProxy::new(
             configuration.configuration_path(),
         )?;
@@ -148,7 +296,13 @@ impl AnisetteHeaders {
             AnisetteMetaError::InvalidArgument("configuration.configuration_path".to_string()),
         )?)?;
         Ok(AnisetteHeadersProviderRes::local(Box::new(
-            ADIProxyAnisetteProvider::new(ssc_adi_proxy, config_path.to_path_buf())?,
+            CachedAnisetteProvider::new(
+                Box::new(ADIProxyAnisetteProvider::new(
+                    ssc_adi_proxy,
+                    config_path.to_path_buf(),
+                )?),
+                header_ttl,
+            ),
         )))
     }
 }
@@ -192,4 +346,73 @@ mod tests {
         );
         Ok(())
     }
+
+    #[cfg(all(unix, feature = "remote-anisette"))]
+    fn invalid_configuration_path() -> std::path::PathBuf {
+        // `configuration_path.to_str()` is required to succeed by `get_ssc_anisette_headers_provider`,
+        // so a non-UTF-8 path is a cheap, deterministic way to make local provisioning fail at
+        // construction time. `StoreServicesCoreADIProxy::new` runs before that check, so the
+        // directory is still created in the working directory.
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        std::path::PathBuf::from(OsStr::from_bytes(b"invalid-\xff-path"))
+    }
+
+    #[cfg(all(unix, feature = "remote-anisette"))]
+    #[test]
+    fn provider_policy_local_only_propagates_local_error() {
+        use crate::{AnisetteConfiguration, AnisetteHeaders, ProviderPolicy};
+
+        let configuration = AnisetteConfiguration::new()
+            .set_configuration_path(invalid_configuration_path())
+            .set_provider_policy(ProviderPolicy::LocalOnly);
+
+        assert!(AnisetteHeaders::get_anisette_headers_provider(configuration).is_err());
+    }
+
+    #[cfg(feature = "remote-anisette")]
+    #[test]
+    fn provider_policy_remote_only_never_touches_local() {
+        use crate::{AnisetteConfiguration, AnisetteHeaders, AnisetteHeadersProviderType, ProviderPolicy};
+
+        let configuration = AnisetteConfiguration::new().set_provider_policy(ProviderPolicy::RemoteOnly);
+
+        let provider = AnisetteHeaders::get_anisette_headers_provider(configuration).unwrap();
+        assert!(matches!(
+            provider.provider_type,
+            AnisetteHeadersProviderType::Remote
+        ));
+    }
+
+    #[cfg(all(unix, feature = "remote-anisette"))]
+    #[test]
+    fn provider_policy_prefer_local_falls_back_to_remote_on_local_error() {
+        use crate::{AnisetteConfiguration, AnisetteHeaders, AnisetteHeadersProviderType, ProviderPolicy};
+
+        let configuration = AnisetteConfiguration::new()
+            .set_configuration_path(invalid_configuration_path())
+            .set_provider_policy(ProviderPolicy::PreferLocal);
+
+        let provider = AnisetteHeaders::get_anisette_headers_provider(configuration).unwrap();
+        assert!(matches!(
+            provider.provider_type,
+            AnisetteHeadersProviderType::Remote
+        ));
+    }
+
+    #[cfg(all(unix, feature = "remote-anisette"))]
+    #[test]
+    fn provider_policy_prefer_remote_tries_remote_first() {
+        use crate::{AnisetteConfiguration, AnisetteHeaders, AnisetteHeadersProviderType, ProviderPolicy};
+
+        let configuration = AnisetteConfiguration::new()
+            .set_configuration_path(invalid_configuration_path())
+            .set_provider_policy(ProviderPolicy::PreferRemote);
+
+        let provider = AnisetteHeaders::get_anisette_headers_provider(configuration).unwrap();
+        assert!(matches!(
+            provider.provider_type,
+            AnisetteHeadersProviderType::Remote
+        ));
+    }
 }
@@ -0,0 +1,84 @@
+//! An [`AnisetteHeadersProvider`] that fetches headers from a remote anisette server (the
+//! "v1" response shape, as served by e.g. `ani.wesbryie.com`) instead of generating them
+//! locally.
+
+use crate::anisette_headers_provider::{self, AnisetteHeadersProvider};
+use crate::http_client_configuration::HttpClientConfiguration;
+use anyhow::Result;
+use std::collections::HashMap;
+use tracing::instrument;
+
+pub struct RemoteAnisetteProvider {
+    anisette_url: String,
+    http_client_configuration: HttpClientConfiguration,
+}
+
+impl RemoteAnisetteProvider {
+    pub fn new(anisette_url: String) -> RemoteAnisetteProvider {
+        RemoteAnisetteProvider::with_http_client_configuration(
+            anisette_url,
+            HttpClientConfiguration::default(),
+        )
+    }
+
+    pub fn with_http_client_configuration(
+        anisette_url: String,
+        http_client_configuration: HttpClientConfiguration,
+    ) -> RemoteAnisetteProvider {
+        RemoteAnisetteProvider {
+            anisette_url,
+            http_client_configuration,
+        }
+    }
+
+    fn url_host(&self) -> &str {
+        self.anisette_url
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or(&self.anisette_url)
+    }
+}
+
+impl AnisetteHeadersProvider for RemoteAnisetteProvider {
+    #[cfg(not(feature = "async"))]
+    #[instrument(
+        name = "get_authentication_headers",
+        skip(self),
+        fields(provider = "remote", host = self.url_host(), request_id = anisette_headers_provider::next_request_id())
+    )]
+    fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>> {
+        let headers = self
+            .http_client_configuration
+            .build_blocking_client()?
+            .get(&self.anisette_url)
+            .send()?
+            .json::<HashMap<String, String>>()?;
+        tracing::debug!(header_count = headers.len(), "fetched anisette headers");
+        Ok(headers)
+    }
+
+    #[cfg(feature = "async")]
+    #[instrument(
+        name = "get_authentication_headers",
+        skip(self),
+        fields(provider = "remote", host = self.url_host(), request_id = anisette_headers_provider::next_request_id())
+    )]
+    fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>> {
+        let http_client_configuration = self.http_client_configuration.clone();
+        let anisette_url = self.anisette_url.clone();
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async move {
+                let headers = http_client_configuration
+                    .build_client()?
+                    .get(&anisette_url)
+                    .send()
+                    .await?
+                    .json::<HashMap<String, String>>()
+                    .await?;
+                tracing::debug!(header_count = headers.len(), "fetched anisette headers");
+                Ok(headers)
+            })
+        })
+    }
+}
@@ -0,0 +1,100 @@
+//! An [`AnisetteHeadersProvider`] that speaks the anisette-v3 protocol (as served by e.g.
+//! `ani.sidestore.io`), which wraps the header map in a small JSON envelope instead of returning
+//! it bare.
+
+use crate::anisette_headers_provider::{self, AnisetteHeadersProvider};
+use crate::http_client_configuration::HttpClientConfiguration;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::instrument;
+
+#[derive(Deserialize)]
+struct AnisetteV3Response {
+    headers: HashMap<String, String>,
+}
+
+pub struct RemoteAnisetteProviderV3 {
+    anisette_url: String,
+    http_client_configuration: HttpClientConfiguration,
+}
+
+impl RemoteAnisetteProviderV3 {
+    pub fn new(anisette_url: String) -> RemoteAnisetteProviderV3 {
+        RemoteAnisetteProviderV3::with_http_client_configuration(
+            anisette_url,
+            HttpClientConfiguration::default(),
+        )
+    }
+
+    pub fn with_http_client_configuration(
+        anisette_url: String,
+        http_client_configuration: HttpClientConfiguration,
+    ) -> RemoteAnisetteProviderV3 {
+        RemoteAnisetteProviderV3 {
+            anisette_url,
+            http_client_configuration,
+        }
+    }
+
+    fn request_url(&self) -> String {
+        format!("{}/v3/client_info", self.anisette_url.trim_end_matches('/'))
+    }
+
+    fn url_host(&self) -> &str {
+        self.anisette_url
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or(&self.anisette_url)
+    }
+}
+
+impl AnisetteHeadersProvider for RemoteAnisetteProviderV3 {
+    #[cfg(not(feature = "async"))]
+    #[instrument(
+        name = "get_authentication_headers",
+        skip(self),
+        fields(provider = "remote", host = self.url_host(), request_id = anisette_headers_provider::next_request_id())
+    )]
+    fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>> {
+        let response = self
+            .http_client_configuration
+            .build_blocking_client()?
+            .get(self.request_url())
+            .send()?
+            .json::<AnisetteV3Response>()?;
+        tracing::debug!(
+            header_count = response.headers.len(),
+            "fetched anisette-v3 headers"
+        );
+        Ok(response.headers)
+    }
+
+    #[cfg(feature = "async")]
+    #[instrument(
+        name = "get_authentication_headers",
+        skip(self),
+        fields(provider = "remote", host = self.url_host(), request_id = anisette_headers_provider::next_request_id())
+    )]
+    fn get_authentication_headers(&mut self) -> Result<HashMap<String, String>> {
+        let http_client_configuration = self.http_client_configuration.clone();
+        let request_url = self.request_url();
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async move {
+                let response = http_client_configuration
+                    .build_client()?
+                    .get(request_url)
+                    .send()
+                    .await?
+                    .json::<AnisetteV3Response>()
+                    .await?;
+                tracing::debug!(
+                    header_count = response.headers.len(),
+                    "fetched anisette-v3 headers"
+                );
+                Ok(response.headers)
+            })
+        })
+    }
+}
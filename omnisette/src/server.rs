@@ -0,0 +1,154 @@
+//! An optional anisette server: exposes any [`AnisetteHeadersProvider`] over HTTP using the
+//! anisette-v3 JSON response shape, so a machine with working local provisioning can serve
+//! headers to other machines that would otherwise need their own `StoreServicesCore` setup.
+
+use crate::{AnisetteConfiguration, AnisetteHeaders, AnisetteHeadersProviderRes};
+use anyhow::{anyhow, Result};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::instrument;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// Read/write timeout applied to every accepted connection, so a slow or silent client can't
+/// wedge the thread serving it (and, since each connection now gets its own thread, can't wedge
+/// the rest of the server either) indefinitely.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Serves anisette-v3 headers over HTTP, backed by any [`AnisetteHeadersProvider`]. Each
+/// connection is handled on its own thread, but provisioning state isn't thread-safe, so actual
+/// header generation is serialized behind a mutex rather than running concurrently.
+pub struct AnisetteServer {
+    provider: Mutex<AnisetteHeadersProviderRes>,
+    /// Set once `serve` binds a Unix domain socket it created, so it can be cleaned up on drop.
+    owned_socket_path: Option<PathBuf>,
+}
+
+impl AnisetteServer {
+    pub fn new(configuration: AnisetteConfiguration) -> Result<AnisetteServer> {
+        let provider = AnisetteHeaders::get_anisette_headers_provider(configuration)?;
+        Ok(AnisetteServer {
+            provider: Mutex::new(provider),
+            owned_socket_path: None,
+        })
+    }
+
+    /// Binds `address` and serves requests until the process exits. A failure to bind `address`
+    /// is returned; errors accepting or handling an individual connection are logged and serving
+    /// continues.
+    ///
+    /// `address` is either a Unix domain socket path, spelled `unix:/run/anisette.sock`, or a
+    /// plain TCP address like `0.0.0.0:6969`. Consumes `self` so the bound socket file (if any)
+    /// is removed as soon as serving stops. Each accepted connection is handled on its own
+    /// thread with a read/write timeout, so one slow or silent client can't wedge the others.
+    pub fn serve(mut self, address: &str) -> Result<()> {
+        let listener = self.bind(address)?;
+        let server = Arc::new(self);
+
+        match listener {
+            Listener::Tcp(listener) => {
+                for stream in listener.incoming() {
+                    let server = Arc::clone(&server);
+                    std::thread::spawn(move || {
+                        if let Ok(stream) = &stream {
+                            let _ = stream.set_read_timeout(Some(CONNECTION_TIMEOUT));
+                            let _ = stream.set_write_timeout(Some(CONNECTION_TIMEOUT));
+                        }
+                        server.serve_one(stream);
+                    });
+                }
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                for stream in listener.incoming() {
+                    let server = Arc::clone(&server);
+                    std::thread::spawn(move || {
+                        if let Ok(stream) = &stream {
+                            let _ = stream.set_read_timeout(Some(CONNECTION_TIMEOUT));
+                            let _ = stream.set_write_timeout(Some(CONNECTION_TIMEOUT));
+                        }
+                        server.serve_one(stream);
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a single accepted connection, logging (rather than propagating) any failure so
+    /// one flaky client or a transient provisioning error can't take down the whole server.
+    fn serve_one<S: Read + Write>(&self, stream: std::io::Result<S>) {
+        let result = match stream {
+            Ok(stream) => self.handle_connection(stream),
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = result {
+            tracing::warn!(error = %err, "anisette server connection failed");
+        }
+    }
+
+    fn bind(&mut self, address: &str) -> Result<Listener> {
+        if let Some(socket_path) = address.strip_prefix("unix:") {
+            #[cfg(unix)]
+            {
+                let socket_path = PathBuf::from(socket_path);
+                if socket_path.exists() {
+                    std::fs::remove_file(&socket_path)?;
+                }
+                let listener = UnixListener::bind(&socket_path)?;
+                self.owned_socket_path = Some(socket_path);
+                return Ok(Listener::Unix(listener));
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = socket_path;
+                return Err(anyhow!(
+                    "unix domain sockets are not supported on this platform"
+                ));
+            }
+        }
+        Ok(Listener::Tcp(TcpListener::bind(address)?))
+    }
+
+    #[instrument(skip(self, stream))]
+    fn handle_connection<S: Read + Write>(&self, mut stream: S) -> Result<()> {
+        // We only ever serve a single bare `GET`, so there's no need for a real HTTP parser:
+        // read and discard the request line/headers, then respond unconditionally.
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request)?;
+
+        let headers = self
+            .provider
+            .lock()
+            .map_err(|_| anyhow!("anisette provider mutex poisoned"))?
+            .provider
+            .get_authentication_headers()?;
+
+        let body = serde_json::json!({ "headers": headers }).to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Drop for AnisetteServer {
+    fn drop(&mut self) {
+        if let Some(socket_path) = &self.owned_socket_path {
+            let _ = std::fs::remove_file(socket_path);
+        }
+    }
+}
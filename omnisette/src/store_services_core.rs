@@ -0,0 +1,46 @@
+//! A [`ConfigurableADIProxy`] named after Apple's `libstoreservicescore` shared library, the same
+//! one used by iTunes/Apple Music on desktop platforms. It does not load that library: it creates
+//! `configuration_path` if missing and reports a machine as provisioned when `adi.pb` exists
+//! there, but `provision_device` and `request_otp` always return an error and nothing is written
+//! under `configuration_path`.
+
+use crate::adi_proxy::{ConfigurableADIProxy, DeviceId};
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+use tracing::instrument;
+
+pub struct StoreServicesCoreADIProxy {
+    provisioning_path: PathBuf,
+}
+
+impl StoreServicesCoreADIProxy {
+    pub fn new(configuration_path: &Path) -> Result<StoreServicesCoreADIProxy> {
+        if !configuration_path.exists() {
+            std::fs::create_dir_all(configuration_path)?;
+        }
+        Ok(StoreServicesCoreADIProxy {
+            provisioning_path: configuration_path.to_path_buf(),
+        })
+    }
+}
+
+impl ConfigurableADIProxy for StoreServicesCoreADIProxy {
+    fn set_provisioning_path(&mut self, path: &str) -> Result<()> {
+        self.provisioning_path = PathBuf::from(path);
+        Ok(())
+    }
+
+    fn is_machine_provisioned(&self, _device_id: DeviceId) -> bool {
+        self.provisioning_path.join("adi.pb").exists()
+    }
+
+    #[instrument(name = "provision_device", skip(self), fields(device_id))]
+    fn provision_device(&mut self, device_id: DeviceId) -> Result<()> {
+        tracing::warn!("StoreServicesCore provisioning is not available on this platform");
+        bail!("StoreServicesCore provisioning is not available on this platform")
+    }
+
+    fn request_otp(&self, _device_id: DeviceId) -> Result<(Vec<u8>, Vec<u8>)> {
+        bail!("StoreServicesCore device is not provisioned")
+    }
+}